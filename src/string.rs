@@ -1,9 +1,13 @@
 use std::borrow::{Borrow, Cow};
+use std::error::Error;
 use std::ffi::CStr;
 use std::fmt;
 use std::ops::{Deref, Index, RangeFull};
 use std::os::raw::c_char;
-use std::str;
+use std::rc::Rc;
+use std::slice;
+use std::str::{self, Utf8Error};
+use std::sync::Arc;
 
 #[macro_export]
 macro_rules! im_str {
@@ -20,6 +24,48 @@ macro_rules! im_str {
     })
 }
 
+/// Returns the index of the first occurrence of `needle` in `haystack`, or `None` if it does not
+/// occur.
+fn memchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+    haystack.iter().position(|&b| b == needle)
+}
+
+/// An error returned from `ImString::from_vec` to indicate that a nul byte was found, or the
+/// bytes were not valid UTF-8.
+///
+/// The error carries back the original `Vec<u8>` so it is not lost, mirroring
+/// `std::ffi::NulError`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImStringNulError {
+    position: usize,
+    bytes: Vec<u8>,
+}
+
+impl ImStringNulError {
+    /// Returns the position of the nul byte, or the first byte that made the input invalid
+    /// UTF-8.
+    pub fn nul_position(&self) -> usize {
+        self.position
+    }
+    /// Consumes this error, returning the underlying vector of bytes which generated the error in
+    /// the first place.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl fmt::Display for ImStringNulError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid ImString data at position {}", self.position)
+    }
+}
+
+impl Error for ImStringNulError {
+    fn description(&self) -> &str {
+        "invalid ImString data"
+    }
+}
+
 /// A UTF-8 encoded, growable, implicitly null-terminated string.
 #[derive(Clone, Hash, Ord, Eq, PartialOrd, PartialEq)]
 pub struct ImString(Vec<u8>);
@@ -41,6 +87,23 @@ impl ImString {
     pub unsafe fn from_utf8_with_nul_unchecked(v: Vec<u8>) -> ImString {
         ImString(v)
     }
+    /// Converts a vector of bytes to an `ImString`, failing if the input is not valid UTF-8 or
+    /// contains an interior nul byte.
+    ///
+    /// Unlike `from_utf8_unchecked`, this checks the input for a nul byte before it ever reaches
+    /// dear imgui, where an interior nul would otherwise silently truncate the string.
+    pub fn from_vec(v: Vec<u8>) -> Result<ImString, ImStringNulError> {
+        match memchr(0, &v) {
+            Some(position) => Err(ImStringNulError { position, bytes: v }),
+            None => match str::from_utf8(&v) {
+                Ok(_) => Ok(unsafe { ImString::from_utf8_unchecked(v) }),
+                Err(error) => Err(ImStringNulError {
+                    position: error.valid_up_to(),
+                    bytes: v,
+                }),
+            },
+        }
+    }
     pub fn clear(&mut self) {
         self.0.clear();
         self.0.push(b'\0');
@@ -72,6 +135,52 @@ impl ImString {
     pub fn as_mut_ptr(&mut self) -> *mut c_char {
         self.0.as_mut_ptr() as *mut _
     }
+    /// Consumes the `ImString` and transfers ownership of the string to a C caller.
+    ///
+    /// The pointer must be returned to Rust and reconstituted using `ImString::from_raw` to be
+    /// properly deallocated. Failing to call `from_raw` leaks the buffer.
+    pub fn into_raw(mut self) -> *mut c_char {
+        self.refresh_len();
+        self.0.push(b'\0');
+        Box::into_raw(self.0.into_boxed_slice()) as *mut c_char
+    }
+    /// Retakes ownership of an `ImString` that was transferred to C via `into_raw`.
+    ///
+    /// # Safety
+    ///
+    /// This should only ever be called with a pointer that was earlier obtained from
+    /// `into_raw`. Other usage (e.g. trying to take ownership of a string that was allocated by
+    /// foreign code) is not guaranteed to be compatible with the allocator used by `ImString`.
+    pub unsafe fn from_raw(ptr: *mut c_char) -> ImString {
+        let len = CStr::from_ptr(ptr).to_bytes_with_nul().len();
+        let parts = slice::from_raw_parts_mut(ptr as *mut u8, len);
+        ImString(Box::from_raw(parts as *mut [u8]).into_vec())
+    }
+    /// Converts the `ImString` into a `Vec<u8>`, not including the trailing nul terminator.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.refresh_len();
+        self.0
+    }
+    /// Converts the `ImString` into a `Vec<u8>`, including the trailing nul terminator.
+    pub fn into_bytes_with_nul(mut self) -> Vec<u8> {
+        self.refresh_len();
+        self.0.push(b'\0');
+        self.0
+    }
+    /// Converts the `ImString` into a `String`, not including the trailing nul terminator.
+    pub fn into_string(self) -> String {
+        unsafe { String::from_utf8_unchecked(self.into_bytes()) }
+    }
+    /// Converts this `ImString` into a boxed `ImStr`.
+    ///
+    /// The buffer is shrunk to fit its contents and terminator first, so an `ImString` with
+    /// spare capacity is reallocated and its bytes copied.
+    pub fn into_boxed_im_str(mut self) -> Box<ImStr> {
+        self.refresh_len();
+        self.0.push(b'\0');
+        let boxed_bytes = self.0.into_boxed_slice();
+        unsafe { Box::from_raw(Box::into_raw(boxed_bytes) as *mut ImStr) }
+    }
 
     /// Updates the buffer length based on the current contents.
     ///
@@ -104,6 +213,18 @@ impl<'a> From<ImString> for Cow<'a, ImStr> {
     }
 }
 
+impl From<ImString> for Rc<ImStr> {
+    fn from(s: ImString) -> Rc<ImStr> {
+        Rc::from(s.into_boxed_im_str())
+    }
+}
+
+impl From<ImString> for Arc<ImStr> {
+    fn from(s: ImString) -> Arc<ImStr> {
+        Arc::from(s.into_boxed_im_str())
+    }
+}
+
 impl<'a, T: ?Sized + AsRef<ImStr>> From<&'a T> for ImString {
     fn from(s: &'a T) -> ImString {
         s.as_ref().to_owned()
@@ -147,6 +268,23 @@ impl fmt::Debug for ImString {
     }
 }
 
+impl fmt::Display for ImString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.to_str(), f)
+    }
+}
+
+impl fmt::Write for ImString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.push(c);
+        Ok(())
+    }
+}
+
 impl Deref for ImString {
     type Target = ImStr;
     fn deref(&self) -> &ImStr {
@@ -158,6 +296,46 @@ impl Deref for ImString {
     }
 }
 
+/// An error returned from `ImStr::from_bytes_with_nul` to indicate that the byte slice was not
+/// valid UTF-8, did not end in a nul byte, or contained a nul byte before the end.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImStrError {
+    /// A nul byte was found before the end of the slice.
+    InteriorNul(usize),
+    /// The slice was not terminated by a nul byte.
+    NotNulTerminated,
+    /// The slice was not valid UTF-8.
+    Utf8(Utf8Error),
+}
+
+impl fmt::Display for ImStrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ImStrError::InteriorNul(position) => {
+                write!(f, "data provided contains an interior nul byte at {}", position)
+            }
+            ImStrError::NotNulTerminated => write!(f, "data provided is not nul terminated"),
+            ImStrError::Utf8(ref error) => write!(f, "data provided is not valid UTF-8: {}", error),
+        }
+    }
+}
+
+impl Error for ImStrError {
+    fn description(&self) -> &str {
+        match *self {
+            ImStrError::InteriorNul(_) => "data provided contains an interior nul byte",
+            ImStrError::NotNulTerminated => "data provided is not nul terminated",
+            ImStrError::Utf8(_) => "data provided is not valid UTF-8",
+        }
+    }
+    fn cause(&self) -> Option<&dyn Error> {
+        match *self {
+            ImStrError::Utf8(ref error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
 /// A UTF-8 encoded, implicitly null-terminated string slice.
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ImStr(CStr);
@@ -175,6 +353,12 @@ impl fmt::Debug for ImStr {
     }
 }
 
+impl fmt::Display for ImStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.to_str(), f)
+    }
+}
+
 impl ImStr {
     pub fn new<S: AsRef<ImStr> + ?Sized>(s: &S) -> &ImStr {
         s.as_ref()
@@ -184,6 +368,18 @@ impl ImStr {
     pub unsafe fn from_utf8_with_nul_unchecked(bytes: &[u8]) -> &ImStr {
         &*(bytes as *const [u8] as *const ImStr)
     }
+    /// Converts a slice of bytes to an imgui-rs string slice, failing if the bytes are not valid
+    /// UTF-8, do not end in a nul byte, or contain a nul byte before the end.
+    pub fn from_bytes_with_nul(bytes: &[u8]) -> Result<&ImStr, ImStrError> {
+        match memchr(0, bytes) {
+            Some(position) if position + 1 == bytes.len() => {
+                str::from_utf8(&bytes[..position]).map_err(ImStrError::Utf8)?;
+                Ok(unsafe { ImStr::from_utf8_with_nul_unchecked(bytes) })
+            }
+            Some(position) => Err(ImStrError::InteriorNul(position)),
+            None => Err(ImStrError::NotNulTerminated),
+        }
+    }
     /// Converts a CStr reference to an imgui-rs string slice without checking for valid UTF-8.
     pub unsafe fn from_cstr_unchecked(value: &CStr) -> &ImStr {
         &*(value as *const CStr as *const ImStr)
@@ -229,3 +425,10 @@ impl ToOwned for ImStr {
         ImString(self.0.to_owned().into_bytes())
     }
 }
+
+impl<'a> From<&'a ImStr> for Box<ImStr> {
+    fn from(s: &'a ImStr) -> Box<ImStr> {
+        let boxed_bytes: Box<[u8]> = Box::from(s.0.to_bytes_with_nul());
+        unsafe { Box::from_raw(Box::into_raw(boxed_bytes) as *mut ImStr) }
+    }
+}